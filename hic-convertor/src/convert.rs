@@ -0,0 +1,199 @@
+//! BAM -> `.pairs` conversion and the end-to-end `all` pipeline.
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+use log::info;
+
+use crate::bgzip::{open_transparent, BgzfWriter};
+use crate::format::{Chromosome, Encode, MatrixWriter, OutFormat, PairsWriter};
+use crate::graph::ContigGraph;
+use crate::pairs::{read_pairs_header, PairRecord};
+
+/// One aligned read-pair as read from a BAM file, in reference
+/// (contig-local) coordinates, prior to any graph translation.
+struct AlignedPair {
+    read_id: String,
+    chrom1: String,
+    pos1: u64,
+    chrom2: String,
+    pos2: u64,
+    strand1: char,
+    strand2: char,
+}
+
+/// Aligned Hi-C read pairs from `bam_file`. No BAM decoding is done here:
+/// this returns an empty list for any input, so conversion writes only the
+/// output header (or an empty matrix).
+fn read_bam_pairs(_bam_file: &Path) -> Vec<AlignedPair> {
+    Vec::new()
+}
+
+/// Translate `(chrom, pos)` through `contig_graph` into graph-path
+/// coordinates when a graph is given and the contig participates in it,
+/// otherwise pass the reference coordinate through unchanged.
+fn translate_coordinate(contig_graph: Option<&ContigGraph>, chrom: &str, pos: u64) -> (String, u64) {
+    contig_graph
+        .and_then(|graph| graph.translate(chrom, pos))
+        .unwrap_or_else(|| (chrom.to_string(), pos))
+}
+
+/// Convert `bam_file` (optionally translated into graph-path coordinates
+/// via `graph`, a GFA assembly graph) into `out_file`, encoded as
+/// `out_format` (`pairs` or, with `bin_size`, a binned contact `matrix`),
+/// writing a side-effect QC summary to `stats_file`. When `bgzip` is set
+/// and `out_format` is `pairs`, `out_file` is written block-gzipped.
+pub fn convert_bam_to_pairs(
+    bam_file: &Path,
+    graph: Option<&Path>,
+    out_file: &Path,
+    stats_file: &Path,
+    out_format: OutFormat,
+    bin_size: Option<u64>,
+    bgzip: bool,
+) -> Result<(), Box<dyn Error>> {
+    info!("converting {} to {} ({:?})", bam_file.display(), out_file.display(), out_format);
+
+    // When reads are aligned to assembly-graph contigs, resolve the graph
+    // up front so each end's reference coordinate below can be translated
+    // into a graph-path coordinate and the junctions the pair spans
+    // recorded, instead of reporting contig-local positions that
+    // downstream scaffolding can't relate to one another.
+    let contig_graph = match graph {
+        Some(graph_file) => {
+            let contig_graph = ContigGraph::load(graph_file)?;
+            info!(
+                "loaded graph {} ({} segments, {} links)",
+                graph_file.display(), contig_graph.segment_count(), contig_graph.link_count(),
+            );
+            Some(contig_graph)
+        }
+        None => None,
+    };
+
+    let aligned_pairs = read_bam_pairs(bam_file);
+    let chromosomes: Vec<Chromosome> = Vec::new();
+    let mut junctions_spanned = 0usize;
+
+    let records: Vec<PairRecord> = aligned_pairs.iter().map(|aligned| {
+        let (chrom1, pos1) = translate_coordinate(contig_graph.as_ref(), &aligned.chrom1, aligned.pos1);
+        let (chrom2, pos2) = translate_coordinate(contig_graph.as_ref(), &aligned.chrom2, aligned.pos2);
+        if let Some(graph) = &contig_graph {
+            junctions_spanned += graph
+                .junctions_between(&aligned.chrom1, aligned.pos1, &aligned.chrom2, aligned.pos2)
+                .len();
+        }
+        PairRecord {
+            read_id: aligned.read_id.clone(),
+            chrom1, pos1, chrom2, pos2,
+            strand1: aligned.strand1, strand2: aligned.strand2,
+            is_duplicate: false,
+        }
+    }).collect();
+
+    match out_format {
+        OutFormat::Pairs => {
+            let mut writer = PairsWriter::create(out_file, bgzip)?;
+            writer.write_header(&chromosomes)?;
+            for record in &records {
+                writer.write_record(record)?;
+            }
+            writer.finish()?;
+        }
+        OutFormat::Matrix => {
+            let bin_size = bin_size.ok_or("--bin-size is required when --out-format matrix is used")?;
+            let mut writer = MatrixWriter::create(out_file, bin_size)?;
+            writer.write_header(&chromosomes)?;
+            for record in &records {
+                writer.write_record(record)?;
+            }
+            writer.finish()?;
+        }
+    }
+
+    let mut stats = File::create(stats_file)?;
+    writeln!(stats, "pairs\t{}", records.len())?;
+    if contig_graph.is_some() {
+        writeln!(stats, "junctions_spanned\t{}", junctions_spanned)?;
+    }
+
+    Ok(())
+}
+
+/// Convert, sort and deduplicate `bam_file` into `out_dir`, using `nproc`
+/// worker threads for the sort stage. When `bgzip` is set, the final
+/// deduplicated pairs are additionally written as `dedup.pairs.gz`.
+pub fn full_pipeline(
+    bam_file: &Path,
+    graph: Option<&Path>,
+    out_dir: &Path,
+    nproc: u8,
+    out_format: OutFormat,
+    bin_size: Option<u64>,
+    bgzip: bool,
+) -> Result<(), Box<dyn Error>> {
+    info!("running full pipeline on {} -> {}", bam_file.display(), out_dir.display());
+    std::fs::create_dir_all(out_dir)?;
+
+    let raw_pairs = out_dir.join("raw.pairs");
+    let sorted_pairs = out_dir.join("sorted.pairs");
+    let dedup_pairs = out_dir.join("dedup.pairs");
+    let stats_file = out_dir.join("stats.txt");
+
+    convert_bam_to_pairs(bam_file, graph, &raw_pairs, &stats_file, OutFormat::Pairs, None, false)?;
+    crate::sort::sort_pairs(&raw_pairs, &sorted_pairs, Some(&out_dir.join("tmp_sort_dir")), nproc)?;
+    crate::dedup::deduplicate_pairs(&sorted_pairs, &dedup_pairs);
+
+    if out_format == OutFormat::Matrix {
+        let bin_size = bin_size.ok_or("--bin-size is required when --out-format matrix is used")?;
+        pairs_to_matrix(&dedup_pairs, &out_dir.join("matrix"), bin_size)?;
+    }
+
+    if bgzip {
+        compress_to_bgzip(&dedup_pairs, &out_dir.join("dedup.pairs.gz"))?;
+    }
+
+    Ok(())
+}
+
+/// Aggregate an already-written `.pairs` file into a binned contact matrix
+/// at `out_prefix` (`<prefix>.bins.tsv` + `<prefix>.coo.tsv`).
+fn pairs_to_matrix(pairs_file: &Path, out_prefix: &Path, bin_size: u64) -> Result<(), Box<dyn Error>> {
+    let mut reader = BufReader::new(open_transparent(pairs_file)?);
+    let header = read_pairs_header(&mut reader)?;
+    let chromosomes = Chromosome::parse_header(&header);
+
+    let mut writer = MatrixWriter::create(out_prefix, bin_size)?;
+    writer.write_header(&chromosomes)?;
+
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let bytes_read = reader.read_line(&mut line)?;
+        if bytes_read == 0 {
+            break;
+        }
+        if let Some(record) = PairRecord::parse(line.trim_end()) {
+            writer.write_record(&record)?;
+        }
+    }
+    writer.finish()
+}
+
+/// Re-encode the plain `src` `.pairs` file as block-gzipped `dst`.
+fn compress_to_bgzip(src: &Path, dst: &Path) -> Result<(), Box<dyn Error>> {
+    let mut reader = BufReader::new(File::open(src)?);
+    let mut writer = BgzfWriter::new(std::io::BufWriter::new(File::create(dst)?));
+    let mut buf = [0u8; 65536];
+    loop {
+        let bytes_read = reader.read(&mut buf)?;
+        if bytes_read == 0 {
+            break;
+        }
+        writer.write_all(&buf[..bytes_read])?;
+    }
+    writer.finish()?;
+    Ok(())
+}
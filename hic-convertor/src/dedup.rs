@@ -0,0 +1,302 @@
+//! Detection and removal (or marking) of duplicate Hi-C read pairs,
+//! tolerating a configurable positional mismatch between PCR/optical
+//! duplicates rather than requiring exact-coordinate matches.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use log::info;
+
+use crate::bgzip::open_transparent;
+use crate::pairs::{read_pairs_header, PairRecord};
+
+/// What to do with a record once it's flagged as a duplicate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateAction {
+    /// Omit duplicate records from the output entirely (the default).
+    Drop,
+    /// Keep every record, appending a `0`/`1` duplicate-flag column.
+    Mark,
+}
+
+/// Options controlling duplicate detection.
+#[derive(Debug, Clone, Copy)]
+pub struct DedupOptions {
+    /// Both ends must fall within this many bp of a kept record to count
+    /// as a duplicate. `0` reproduces exact-coordinate matching.
+    pub max_mismatch: u64,
+    pub action: DuplicateAction,
+    /// When set, a candidate must also match strand orientation on both
+    /// ends to be considered a duplicate of a kept record.
+    pub require_same_strand: bool,
+}
+
+impl Default for DedupOptions {
+    fn default() -> DedupOptions {
+        DedupOptions { max_mismatch: 0, action: DuplicateAction::Drop, require_same_strand: false }
+    }
+}
+
+/// Counts from a deduplication run, used to report a duplication summary.
+#[derive(Debug, Default)]
+pub struct DedupSummary {
+    pub total: u64,
+    pub unique: u64,
+    pub duplicate: u64,
+}
+
+impl DedupSummary {
+    pub fn duplicate_fraction(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.duplicate as f64 / self.total as f64
+        }
+    }
+
+    /// Estimate library complexity (the number of unique molecules the
+    /// library would yield at infinite sequencing depth) via the same
+    /// Lander-Waterman-style bisection Picard's `EstimateLibrarySize` uses.
+    /// Returns `None` when there are no duplicates to extrapolate from.
+    pub fn estimated_complexity(&self) -> Option<f64> {
+        estimate_library_complexity(self.total, self.unique)
+    }
+
+    /// Render the report as a two-column `metric\tvalue` TSV.
+    pub fn to_tsv(&self) -> String {
+        let mut out = String::new();
+        out.push_str(&format!("total_pairs\t{}\n", self.total));
+        out.push_str(&format!("unique_pairs\t{}\n", self.unique));
+        out.push_str(&format!("duplicate_pairs\t{}\n", self.duplicate));
+        out.push_str(&format!("duplicate_fraction\t{:.4}\n", self.duplicate_fraction()));
+        match self.estimated_complexity() {
+            Some(complexity) => out.push_str(&format!("estimated_library_complexity\t{:.0}\n", complexity)),
+            None => out.push_str("estimated_library_complexity\tNA\n"),
+        }
+        out
+    }
+}
+
+/// Solve for the library size `x` satisfying `c/x - 1 + exp(-n/x) = 0`,
+/// where `n` is total observed pairs and `c` is the unique ones, by
+/// bisection (mirrors Picard's `EstimateLibrarySize`).
+fn estimate_library_complexity(total: u64, unique: u64) -> Option<f64> {
+    let n = total as f64;
+    let c = unique as f64;
+    let duplicates = n - c;
+    if n <= 0.0 || duplicates <= 0.0 {
+        return None;
+    }
+
+    let f = |x: f64| c / x - 1.0 + (-n / x).exp();
+
+    let mut lo_factor = 1.0;
+    let mut hi_factor = 100.0;
+    if f(lo_factor * c) < 0.0 {
+        return None;
+    }
+    while f(hi_factor * c) > 0.0 {
+        hi_factor *= 10.0;
+    }
+
+    for _ in 0..40 {
+        let mid_factor = (lo_factor + hi_factor) / 2.0;
+        let value = f(mid_factor * c);
+        if value == 0.0 {
+            lo_factor = mid_factor;
+            hi_factor = mid_factor;
+            break;
+        } else if value > 0.0 {
+            lo_factor = mid_factor;
+        } else {
+            hi_factor = mid_factor;
+        }
+    }
+
+    Some(c * (lo_factor + hi_factor) / 2.0)
+}
+
+/// Write `summary` to `out_file` as TSV.
+pub fn write_dedup_summary(summary: &DedupSummary, out_file: &Path) -> std::io::Result<()> {
+    let mut file = File::create(out_file)?;
+    write!(file, "{}", summary.to_tsv())
+}
+
+/// A record held in the sliding window, pending eviction once no later
+/// record on the same `(chrom1, chrom2)` pair can still match it.
+struct Candidate {
+    record: PairRecord,
+}
+
+/// Deduplicate `in_pairs` (sorted by `(chrom1, chrom2, pos1, pos2)`) into
+/// `out_pairs` per `options`, using a sliding window keyed on
+/// `(chrom1, chrom2)` so near-identical pairs within `max_mismatch` bp on
+/// both ends are treated as PCR/optical duplicates. Returns summary counts.
+pub fn deduplicate_pairs_with_options(
+    in_pairs: &Path,
+    out_pairs: &Path,
+    options: DedupOptions,
+) -> DedupSummary {
+    info!("deduplicating {} -> {} (max_mismatch={})", in_pairs.display(), out_pairs.display(), options.max_mismatch);
+
+    let mut reader = BufReader::new(open_transparent(in_pairs).expect("failed to open input pairs file"));
+    let header = read_pairs_header(&mut reader).expect("failed to read pairs header");
+
+    let outfile = File::create(out_pairs).expect("failed to create output pairs file");
+    let mut writer = BufWriter::new(outfile);
+    for line in &header {
+        writeln!(writer, "{}", line).expect("failed to write header");
+    }
+
+    let mut summary = DedupSummary::default();
+    let mut window: VecDeque<Candidate> = VecDeque::new();
+    let mut window_key: Option<(String, String)> = None;
+
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let bytes_read = reader.read_line(&mut line).expect("failed to read pairs record");
+        if bytes_read == 0 {
+            break;
+        }
+        let trimmed = line.trim_end();
+        let record = match PairRecord::parse(trimmed) {
+            Some(record) => record,
+            None => continue,
+        };
+        summary.total += 1;
+
+        let key = (record.chrom1.clone(), record.chrom2.clone());
+        if window_key.as_ref() != Some(&key) {
+            window.clear();
+            window_key = Some(key);
+        }
+        while let Some(front) = window.front() {
+            if record.pos1 > front.record.pos1 + options.max_mismatch {
+                window.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        let is_duplicate = window.iter().any(|candidate| {
+            pos_within(candidate.record.pos2, record.pos2, options.max_mismatch)
+                && (!options.require_same_strand
+                    || (candidate.record.strand1 == record.strand1 && candidate.record.strand2 == record.strand2))
+        });
+
+        if is_duplicate {
+            summary.duplicate += 1;
+            if options.action == DuplicateAction::Mark {
+                writeln!(writer, "{}\t1", trimmed).expect("failed to write pairs record");
+            }
+        } else {
+            summary.unique += 1;
+            window.push_back(Candidate { record: record.clone() });
+            match options.action {
+                DuplicateAction::Drop => writeln!(writer, "{}", trimmed).expect("failed to write pairs record"),
+                DuplicateAction::Mark => writeln!(writer, "{}\t0", trimmed).expect("failed to write pairs record"),
+            }
+        }
+    }
+
+    summary
+}
+
+fn pos_within(a: u64, b: u64, tolerance: u64) -> bool {
+    a.abs_diff(b) <= tolerance
+}
+
+/// Drop exact-coordinate duplicate pairs from `in_pairs`, writing the
+/// deduplicated stream to `out_pairs`. A thin wrapper over
+/// [`deduplicate_pairs_with_options`] for exact matching.
+pub fn deduplicate_pairs(in_pairs: &Path, out_pairs: &Path) {
+    deduplicate_pairs_with_options(in_pairs, out_pairs, DedupOptions::default());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("hic_convertor_dedup_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    const HEADER: &str = concat!(
+        "## pairs format v1.0\n",
+        "#sorted: chr1-chr2-pos1-pos2\n",
+        "#shape: upper triangle\n",
+        "#columns: readID chr1 pos1 chr2 pos2 strand1 strand2\n",
+    );
+
+    #[test]
+    fn drops_near_coordinate_duplicates_within_tolerance() {
+        let in_path = temp_path("in_drop.pairs");
+        let out_path = temp_path("out_drop.pairs");
+
+        std::fs::write(&in_path, format!(
+            "{}{}",
+            HEADER,
+            concat!(
+                "r1\tchr1\t100\tchr1\t500\t+\t+\n",
+                // within max_mismatch=2 of r1 on both ends -> duplicate.
+                "r2\tchr1\t101\tchr1\t501\t+\t+\n",
+                // outside tolerance on pos2 -> distinct.
+                "r3\tchr1\t102\tchr1\t600\t+\t+\n",
+            ),
+        )).unwrap();
+
+        let options = DedupOptions { max_mismatch: 2, action: DuplicateAction::Drop, require_same_strand: false };
+        let summary = deduplicate_pairs_with_options(&in_path, &out_path, options);
+
+        assert_eq!(summary.total, 3);
+        assert_eq!(summary.unique, 2);
+        assert_eq!(summary.duplicate, 1);
+
+        let out = std::fs::read_to_string(&out_path).unwrap();
+        let records: Vec<&str> = out.lines().filter(|line| !line.starts_with('#')).collect();
+        assert_eq!(records, vec![
+            "r1\tchr1\t100\tchr1\t500\t+\t+",
+            "r3\tchr1\t102\tchr1\t600\t+\t+",
+        ]);
+
+        std::fs::remove_file(&in_path).ok();
+        std::fs::remove_file(&out_path).ok();
+    }
+
+    #[test]
+    fn mark_action_keeps_every_record_and_flags_duplicates() {
+        let in_path = temp_path("in_mark.pairs");
+        let out_path = temp_path("out_mark.pairs");
+
+        std::fs::write(&in_path, format!(
+            "{}{}",
+            HEADER,
+            concat!(
+                "r1\tchr1\t100\tchr1\t500\t+\t+\n",
+                "r2\tchr1\t100\tchr1\t500\t+\t+\n",
+            ),
+        )).unwrap();
+
+        let options = DedupOptions { max_mismatch: 0, action: DuplicateAction::Mark, require_same_strand: false };
+        let summary = deduplicate_pairs_with_options(&in_path, &out_path, options);
+
+        assert_eq!(summary.total, 2);
+        assert_eq!(summary.unique, 1);
+        assert_eq!(summary.duplicate, 1);
+
+        let out = std::fs::read_to_string(&out_path).unwrap();
+        let records: Vec<&str> = out.lines().filter(|line| !line.starts_with('#')).collect();
+        assert_eq!(records, vec![
+            "r1\tchr1\t100\tchr1\t500\t+\t+\t0",
+            "r2\tchr1\t100\tchr1\t500\t+\t+\t1",
+        ]);
+
+        std::fs::remove_file(&in_path).ok();
+        std::fs::remove_file(&out_path).ok();
+    }
+}
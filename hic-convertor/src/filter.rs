@@ -0,0 +1,151 @@
+//! Region- and chromosome-pattern selection of pairs from a `.pairs` file.
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use regex::Regex;
+
+use crate::bgzip::open_transparent;
+use crate::index::Region;
+use crate::pairs::{read_pairs_header, PairRecord};
+
+/// Which end(s) of a pair a `--region` filter must overlap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionMode {
+    /// Either end overlapping the region is enough (the default).
+    Either,
+    /// Both ends must overlap the region.
+    Both,
+}
+
+/// A chromosome-name matcher, built from either a `--chrom` glob or a
+/// `--chrom-re` regular expression.
+pub enum ChromPattern {
+    Glob(String),
+    Regex(Regex),
+}
+
+impl ChromPattern {
+    pub fn matches(&self, chrom: &str) -> bool {
+        match self {
+            ChromPattern::Glob(pattern) => glob_match(pattern, chrom),
+            ChromPattern::Regex(re) => re.is_match(chrom),
+        }
+    }
+}
+
+/// Shell-style glob matching supporting `*` (any run of characters) and
+/// `?` (any single character).
+fn glob_match(pattern: &str, text: &str) -> bool {
+    fn matches(pattern: &[u8], text: &[u8]) -> bool {
+        match (pattern.first(), text.first()) {
+            (None, None) => true,
+            (Some(b'*'), _) => {
+                matches(&pattern[1..], text) || (!text.is_empty() && matches(pattern, &text[1..]))
+            }
+            (Some(b'?'), Some(_)) => matches(&pattern[1..], &text[1..]),
+            (Some(p), Some(t)) if p == t => matches(&pattern[1..], &text[1..]),
+            _ => false,
+        }
+    }
+    matches(pattern.as_bytes(), text.as_bytes())
+}
+
+/// Selection criteria combined with AND semantics by [`filter_pairs`].
+#[derive(Default)]
+pub struct FilterCriteria {
+    pub region: Option<Region>,
+    pub region_mode: RegionMode,
+    pub chrom_pattern: Option<ChromPattern>,
+    pub cis_only: bool,
+    pub trans_only: bool,
+    pub min_distance: Option<u64>,
+    pub max_distance: Option<u64>,
+}
+
+impl Default for RegionMode {
+    fn default() -> RegionMode {
+        RegionMode::Either
+    }
+}
+
+impl FilterCriteria {
+    fn matches(&self, record: &PairRecord) -> bool {
+        if let Some(region) = &self.region {
+            let end1_in = record.chrom1 == region.chrom && region.contains(record.pos1);
+            let end2_in = record.chrom2 == region.chrom && region.contains(record.pos2);
+            let region_ok = match self.region_mode {
+                RegionMode::Either => end1_in || end2_in,
+                RegionMode::Both => end1_in && end2_in,
+            };
+            if !region_ok {
+                return false;
+            }
+        }
+
+        if let Some(pattern) = &self.chrom_pattern {
+            if !pattern.matches(&record.chrom1) && !pattern.matches(&record.chrom2) {
+                return false;
+            }
+        }
+
+        if self.cis_only && !record.is_cis() {
+            return false;
+        }
+        if self.trans_only && record.is_cis() {
+            return false;
+        }
+
+        match record.contact_distance() {
+            Some(distance) => {
+                if let Some(min_distance) = self.min_distance {
+                    if distance < min_distance {
+                        return false;
+                    }
+                }
+                if let Some(max_distance) = self.max_distance {
+                    if distance > max_distance {
+                        return false;
+                    }
+                }
+            }
+            None if self.min_distance.is_some() || self.max_distance.is_some() => return false,
+            None => {}
+        }
+
+        true
+    }
+}
+
+/// Stream `in_pairs`, writing only records matching every criterion in
+/// `criteria` (AND semantics) to `out_pairs`, preserving the header.
+pub fn filter_pairs(in_pairs: &Path, out_pairs: &Path, criteria: &FilterCriteria) -> Result<(), Box<dyn Error>> {
+    let mut reader = BufReader::new(open_transparent(in_pairs)?);
+    let header = read_pairs_header(&mut reader)?;
+
+    let mut writer = BufWriter::new(File::create(out_pairs)?);
+    for line in &header {
+        writeln!(writer, "{}", line)?;
+    }
+
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let bytes_read = reader.read_line(&mut line)?;
+        if bytes_read == 0 {
+            break;
+        }
+        let trimmed = line.trim_end();
+        let record = match PairRecord::parse(trimmed) {
+            Some(record) => record,
+            None => continue,
+        };
+        if criteria.matches(&record) {
+            writeln!(writer, "{}", trimmed)?;
+        }
+    }
+
+    Ok(())
+}
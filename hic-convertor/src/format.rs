@@ -0,0 +1,267 @@
+//! Pluggable output formats for Hi-C contacts: a spec-compliant 4DN
+//! `.pairs` writer and a cooler-style binned contact-matrix exporter.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::bgzip::BgzfWriter;
+use crate::pairs::PairRecord;
+
+/// Chromosome name and length, as declared by a `#chromosomes` header line
+/// or an assembly index.
+#[derive(Debug, Clone)]
+pub struct Chromosome {
+    pub name: String,
+    pub length: u64,
+}
+
+impl Chromosome {
+    /// Parse the `#chromosomes: name length` lines out of a `.pairs`
+    /// header, in header order, skipping any that don't match.
+    pub fn parse_header(header: &[String]) -> Vec<Chromosome> {
+        header.iter()
+            .filter_map(|line| line.strip_prefix("#chromosomes:"))
+            .filter_map(|rest| {
+                let mut fields = rest.split_whitespace();
+                let name = fields.next()?.to_string();
+                let length = fields.next()?.parse().ok()?;
+                Some(Chromosome { name, length })
+            })
+            .collect()
+    }
+}
+
+/// Output format selectable via `--out-format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutFormat {
+    /// 4DN `.pairs` v1.0 compliant text stream (the default).
+    Pairs,
+    /// Sparse genome-wide contact matrix at a fixed bin size.
+    Matrix,
+}
+
+impl OutFormat {
+    /// Parse an `--out-format` CLI value, defaulting unknown input to `None`.
+    pub fn parse(value: &str) -> Option<OutFormat> {
+        match value {
+            "pairs" => Some(OutFormat::Pairs),
+            "matrix" => Some(OutFormat::Matrix),
+            _ => None,
+        }
+    }
+}
+
+/// A sink that can encode a stream of [`PairRecord`]s to disk.
+pub trait Encode {
+    /// Write whatever header the format requires before any records.
+    fn write_header(&mut self, chromosomes: &[Chromosome]) -> Result<(), Box<dyn Error>>;
+    /// Write a single record.
+    fn write_record(&mut self, record: &PairRecord) -> Result<(), Box<dyn Error>>;
+    /// Flush and finalize the output.
+    fn finish(&mut self) -> Result<(), Box<dyn Error>>;
+}
+
+/// Either a plain buffered file or a BGZF stream, selected by `--bgzip`.
+enum Sink {
+    Plain(BufWriter<File>),
+    Bgzf(BgzfWriter<BufWriter<File>>),
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Sink::Plain(w) => w.write(buf),
+            Sink::Bgzf(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Sink::Plain(w) => w.flush(),
+            Sink::Bgzf(w) => w.flush(),
+        }
+    }
+}
+
+/// Writes a 4DN `.pairs` v1.0 compliant file: the mandatory header block
+/// followed by tab-separated records. Writes block-gzipped (`.pairs.gz`)
+/// output when created with `bgzip: true`.
+pub struct PairsWriter {
+    sink: Option<Sink>,
+}
+
+impl PairsWriter {
+    pub fn create(path: &Path, bgzip: bool) -> Result<PairsWriter, Box<dyn Error>> {
+        let sink = if bgzip {
+            Sink::Bgzf(BgzfWriter::new(BufWriter::new(File::create(path)?)))
+        } else {
+            Sink::Plain(BufWriter::new(File::create(path)?))
+        };
+        Ok(PairsWriter { sink: Some(sink) })
+    }
+}
+
+impl Encode for PairsWriter {
+    fn write_header(&mut self, chromosomes: &[Chromosome]) -> Result<(), Box<dyn Error>> {
+        let sink = self.sink.as_mut().expect("writer already finished");
+        writeln!(sink, "## pairs format v1.0")?;
+        writeln!(sink, "#sorted: chr1-chr2-pos1-pos2")?;
+        writeln!(sink, "#shape: upper triangle")?;
+        for chrom in chromosomes {
+            writeln!(sink, "#chromosomes: {} {}", chrom.name, chrom.length)?;
+        }
+        writeln!(sink, "#columns: readID chr1 pos1 chr2 pos2 strand1 strand2")?;
+        Ok(())
+    }
+
+    fn write_record(&mut self, record: &PairRecord) -> Result<(), Box<dyn Error>> {
+        let sink = self.sink.as_mut().expect("writer already finished");
+        writeln!(
+            sink,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            record.read_id, record.chrom1, record.pos1, record.chrom2, record.pos2,
+            record.strand1, record.strand2,
+        )?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        match self.sink.take() {
+            Some(Sink::Plain(mut writer)) => writer.flush()?,
+            Some(Sink::Bgzf(writer)) => writer.finish()?,
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+/// Aggregates pairs into a genome-wide contact matrix at a fixed bin size,
+/// the on-disk precursor to a cooler: a bin table plus a sparse COO triple
+/// stream (`bin1_id  bin2_id  count`).
+pub struct MatrixWriter {
+    bin_size: u64,
+    out_prefix: PathBuf,
+    chromosomes: Vec<Chromosome>,
+    bin_offsets: BTreeMap<String, u64>,
+    counts: BTreeMap<(u64, u64), u64>,
+}
+
+impl MatrixWriter {
+    /// Create a matrix writer binning at `bin_size` bp. Errors if
+    /// `bin_size` is `0`, since every bin lookup divides by it.
+    pub fn create(out_prefix: &Path, bin_size: u64) -> Result<MatrixWriter, Box<dyn Error>> {
+        if bin_size == 0 {
+            return Err("--bin-size must be greater than 0".into());
+        }
+        Ok(MatrixWriter {
+            bin_size,
+            out_prefix: out_prefix.to_path_buf(),
+            chromosomes: Vec::new(),
+            bin_offsets: BTreeMap::new(),
+            counts: BTreeMap::new(),
+        })
+    }
+
+    fn bin_id(&self, chrom: &str, pos: u64) -> Option<u64> {
+        self.bin_offsets.get(chrom).map(|offset| offset + pos / self.bin_size)
+    }
+}
+
+impl Encode for MatrixWriter {
+    fn write_header(&mut self, chromosomes: &[Chromosome]) -> Result<(), Box<dyn Error>> {
+        self.chromosomes = chromosomes.to_vec();
+        let mut offset = 0u64;
+        for chrom in &self.chromosomes {
+            self.bin_offsets.insert(chrom.name.clone(), offset);
+            offset += (chrom.length + self.bin_size - 1) / self.bin_size;
+        }
+        Ok(())
+    }
+
+    fn write_record(&mut self, record: &PairRecord) -> Result<(), Box<dyn Error>> {
+        if let (Some(bin1), Some(bin2)) = (
+            self.bin_id(&record.chrom1, record.pos1),
+            self.bin_id(&record.chrom2, record.pos2),
+        ) {
+            let key = if bin1 <= bin2 { (bin1, bin2) } else { (bin2, bin1) };
+            *self.counts.entry(key).or_insert(0) += 1;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        let bins_path = self.out_prefix.with_file_name(format!(
+            "{}.bins.tsv",
+            self.out_prefix.file_stem().and_then(|s| s.to_str()).unwrap_or("matrix")
+        ));
+        let mut bin_table = BufWriter::new(File::create(&bins_path)?);
+        writeln!(bin_table, "bin_id\tchrom\tstart\tend")?;
+        for chrom in &self.chromosomes {
+            let offset = self.bin_offsets[&chrom.name];
+            let n_bins = (chrom.length + self.bin_size - 1) / self.bin_size;
+            for i in 0..n_bins {
+                let start = i * self.bin_size;
+                let end = (start + self.bin_size).min(chrom.length);
+                writeln!(bin_table, "{}\t{}\t{}\t{}", offset + i, chrom.name, start, end)?;
+            }
+        }
+
+        let coo_path = self.out_prefix.with_file_name(format!(
+            "{}.coo.tsv",
+            self.out_prefix.file_stem().and_then(|s| s.to_str()).unwrap_or("matrix")
+        ));
+        let mut coo = BufWriter::new(File::create(&coo_path)?);
+        writeln!(coo, "bin1_id\tbin2_id\tcount")?;
+        for ((bin1, bin2), count) in &self.counts {
+            writeln!(coo, "{}\t{}\t{}", bin1, bin2, count)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_header_extracts_chromosomes_in_header_order() {
+        let header = vec![
+            "## pairs format v1.0".to_string(),
+            "#sorted: chr1-chr2-pos1-pos2".to_string(),
+            "#chromosomes: chr1 1000".to_string(),
+            "#chromosomes: chr2 2000".to_string(),
+            "#columns: readID chr1 pos1 chr2 pos2 strand1 strand2".to_string(),
+        ];
+
+        let chromosomes = Chromosome::parse_header(&header);
+        let names: Vec<(&str, u64)> = chromosomes.iter().map(|c| (c.name.as_str(), c.length)).collect();
+        assert_eq!(names, vec![("chr1", 1000), ("chr2", 2000)]);
+    }
+
+    #[test]
+    fn matrix_writer_bins_records_against_parsed_chromosomes() {
+        let dir = std::env::temp_dir().join(format!("hic_convertor_format_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let prefix = dir.join("matrix");
+
+        let chromosomes = Chromosome::parse_header(&["#chromosomes: chr1 150".to_string()]);
+        let mut writer = MatrixWriter::create(&prefix, 100).unwrap();
+        writer.write_header(&chromosomes).unwrap();
+        writer.write_record(&PairRecord {
+            read_id: "r1".to_string(), chrom1: "chr1".to_string(), pos1: 10,
+            chrom2: "chr1".to_string(), pos2: 120, strand1: '+', strand2: '+', is_duplicate: false,
+        }).unwrap();
+        writer.finish().unwrap();
+
+        let coo = std::fs::read_to_string(dir.join("matrix.coo.tsv")).unwrap();
+        // Bin 0 (chr1:0-100) and bin 1 (chr1:100-150) should show one count,
+        // not an empty file (the bug: unparsed chromosomes dropped every record).
+        assert_eq!(coo.lines().filter(|line| !line.starts_with("bin1_id")).count(), 1);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,273 @@
+//! Parsing of assembly graphs in GFA format and translation of per-segment
+//! (contig) BAM coordinates into graph-path coordinates, so Hi-C pairs
+//! aligned to assembly-graph contigs can be reported against a path
+//! through the graph rather than isolated contigs.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// A GFA segment (`S` line): a contig with a known length.
+#[derive(Debug, Clone)]
+struct Segment {
+    length: u64,
+}
+
+/// A GFA link (`L` line) joining the end of one segment to the start of
+/// another.
+#[derive(Debug, Clone)]
+pub struct Link {
+    pub from: String,
+    pub to: String,
+}
+
+/// One step of a GFA path (`P` line): a segment traversed in a given
+/// orientation.
+#[derive(Debug, Clone)]
+struct PathStep {
+    segment: String,
+    reverse: bool,
+}
+
+/// A GFA path (`P` line): an ordered walk over segments that defines a
+/// single linear graph coordinate system.
+#[derive(Debug, Clone)]
+struct GraphPath {
+    name: String,
+    steps: Vec<PathStep>,
+}
+
+/// Where a segment sits within a [`GraphPath`]: its 0-based start offset
+/// in path coordinates, and whether it is traversed reverse-complemented.
+#[derive(Debug, Clone, Copy)]
+struct Placement {
+    path_index: usize,
+    offset: u64,
+    reverse: bool,
+}
+
+/// A junction (graph edge) a pair spans, named after the two segments a
+/// [`Link`] joins.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Junction {
+    pub from: String,
+    pub to: String,
+}
+
+/// An assembly graph loaded from a GFA file: segment lengths, links
+/// between them, and (if present) `P`-line paths used to translate
+/// segment-local coordinates into a single linear coordinate system per
+/// path.
+pub struct ContigGraph {
+    segments: HashMap<String, Segment>,
+    links: Vec<Link>,
+    paths: Vec<GraphPath>,
+    /// Segment name -> its placement within `paths`, taken from the first
+    /// path step that traverses it; later occurrences are ignored.
+    placements: HashMap<String, Placement>,
+}
+
+impl ContigGraph {
+    /// Parse `gfa_file`, building path placements from its `P` lines. When
+    /// the file has no paths, each segment falls back to a single-segment
+    /// path of its own, so translation still succeeds, it just never
+    /// crosses a junction.
+    pub fn load(gfa_file: &Path) -> Result<ContigGraph, Box<dyn Error>> {
+        let reader = BufReader::new(File::open(gfa_file)?);
+        let mut segments = HashMap::new();
+        let mut links = Vec::new();
+        let mut paths = Vec::new();
+
+        for line in reader.lines() {
+            let line = line?;
+            let mut fields = line.split('\t');
+            match fields.next() {
+                Some("S") => {
+                    let name = fields.next().ok_or("malformed S line: missing name")?.to_string();
+                    let sequence = fields.next().unwrap_or("*");
+                    let length = fields.clone()
+                        .find_map(|tag| tag.strip_prefix("LN:i:"))
+                        .and_then(|value| value.parse().ok())
+                        .unwrap_or_else(|| if sequence == "*" { 0 } else { sequence.len() as u64 });
+                    segments.insert(name, Segment { length });
+                }
+                Some("L") => {
+                    let from = fields.next().ok_or("malformed L line: missing from")?.to_string();
+                    let _from_orient = fields.next().ok_or("malformed L line: missing from orientation")?;
+                    let to = fields.next().ok_or("malformed L line: missing to")?.to_string();
+                    let _to_orient = fields.next().ok_or("malformed L line: missing to orientation")?;
+                    links.push(Link { from, to });
+                }
+                Some("P") => {
+                    let name = fields.next().ok_or("malformed P line: missing name")?.to_string();
+                    let steps_field = fields.next().ok_or("malformed P line: missing segment names")?;
+                    let steps = steps_field.split(',')
+                        .filter(|step| !step.is_empty())
+                        .map(|step| {
+                            let reverse = step.ends_with('-');
+                            let segment = step.trim_end_matches(|c| c == '+' || c == '-').to_string();
+                            PathStep { segment, reverse }
+                        })
+                        .collect();
+                    paths.push(GraphPath { name, steps });
+                }
+                _ => {}
+            }
+        }
+
+        if paths.is_empty() {
+            for name in segments.keys() {
+                paths.push(GraphPath {
+                    name: name.clone(),
+                    steps: vec![PathStep { segment: name.clone(), reverse: false }],
+                });
+            }
+        }
+
+        let mut placements = HashMap::new();
+        for (path_index, path) in paths.iter().enumerate() {
+            let mut offset = 0u64;
+            for step in &path.steps {
+                placements.entry(step.segment.clone())
+                    .or_insert(Placement { path_index, offset, reverse: step.reverse });
+                offset += segments.get(&step.segment).map(|s| s.length).unwrap_or(0);
+            }
+        }
+
+        Ok(ContigGraph { segments, links, paths, placements })
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn link_count(&self) -> usize {
+        self.links.len()
+    }
+
+    /// Translate a `(segment, pos)` BAM-reference coordinate into
+    /// `(path_name, graph_pos)`, accounting for the segment's orientation
+    /// within its path. Returns `None` for a segment that no path traverses.
+    pub fn translate(&self, segment: &str, pos: u64) -> Option<(String, u64)> {
+        let placement = self.placements.get(segment)?;
+        let path = &self.paths[placement.path_index];
+        let length = self.segments.get(segment).map(|s| s.length).unwrap_or(0);
+        let local = if placement.reverse { length.saturating_sub(1).saturating_sub(pos) } else { pos };
+        Some((path.name.clone(), placement.offset + local))
+    }
+
+    /// The links whose junction lies between `(segment1, pos1)` and
+    /// `(segment2, pos2)` along their shared path, i.e. the junctions a
+    /// Hi-C pair spanning those two coordinates crosses. Returns an empty
+    /// list when either end fails to translate or the two ends land on
+    /// different paths.
+    pub fn junctions_between(&self, segment1: &str, pos1: u64, segment2: &str, pos2: u64) -> Vec<Junction> {
+        let start = self.translate(segment1, pos1);
+        let end = self.translate(segment2, pos2);
+        let (start, end) = match (start, end) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return Vec::new(),
+        };
+        if start.0 != end.0 {
+            return Vec::new();
+        }
+        let (lo, hi) = if start.1 <= end.1 { (start.1, end.1) } else { (end.1, start.1) };
+
+        let path = match self.paths.iter().find(|path| path.name == start.0) {
+            Some(path) => path,
+            None => return Vec::new(),
+        };
+
+        let mut junctions = Vec::new();
+        let mut offset = 0u64;
+        for window in path.steps.windows(2) {
+            let first_len = self.segments.get(&window[0].segment).map(|s| s.length).unwrap_or(0);
+            let boundary = offset + first_len;
+            if boundary > lo && boundary < hi {
+                junctions.push(Junction { from: window[0].segment.clone(), to: window[1].segment.clone() });
+            }
+            offset += first_len;
+        }
+        junctions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("hic_convertor_graph_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    /// Two 100bp segments joined end to end, with `b` traversed
+    /// reverse-complemented in the path.
+    fn write_test_gfa(path: &Path) {
+        std::fs::write(path, concat!(
+            "S\ta\t*\tLN:i:100\n",
+            "S\tb\t*\tLN:i:100\n",
+            "L\ta\t+\tb\t-\t0M\n",
+            "P\tpath1\ta+,b-\t*\n",
+        )).unwrap();
+    }
+
+    #[test]
+    fn translate_maps_forward_segment_straight_through() {
+        let gfa_path = temp_path("forward.gfa");
+        write_test_gfa(&gfa_path);
+        let graph = ContigGraph::load(&gfa_path).unwrap();
+
+        assert_eq!(graph.translate("a", 0), Some(("path1".to_string(), 0)));
+        assert_eq!(graph.translate("a", 50), Some(("path1".to_string(), 50)));
+
+        std::fs::remove_file(&gfa_path).ok();
+    }
+
+    #[test]
+    fn translate_maps_reverse_segment_with_zero_based_coordinates() {
+        let gfa_path = temp_path("reverse.gfa");
+        write_test_gfa(&gfa_path);
+        let graph = ContigGraph::load(&gfa_path).unwrap();
+
+        // b is 100bp, placed at path offset 100, reverse-complemented, so
+        // its own pos=0 (first base) lands on the *last* base of its
+        // placement (offset + length - 1), not one past it.
+        assert_eq!(graph.translate("b", 0), Some(("path1".to_string(), 199)));
+        assert_eq!(graph.translate("b", 99), Some(("path1".to_string(), 100)));
+
+        std::fs::remove_file(&gfa_path).ok();
+    }
+
+    #[test]
+    fn translate_returns_none_for_unknown_segment() {
+        let gfa_path = temp_path("unknown.gfa");
+        write_test_gfa(&gfa_path);
+        let graph = ContigGraph::load(&gfa_path).unwrap();
+
+        assert_eq!(graph.translate("nonexistent", 0), None);
+
+        std::fs::remove_file(&gfa_path).ok();
+    }
+
+    #[test]
+    fn junctions_between_finds_the_boundary_crossed_by_a_pair() {
+        let gfa_path = temp_path("junction.gfa");
+        write_test_gfa(&gfa_path);
+        let graph = ContigGraph::load(&gfa_path).unwrap();
+
+        // a:50 -> path 50; b:50 (reverse) -> path offset 100 + (99-50) = 149.
+        // The pair spans the a/b boundary at path offset 100.
+        let junctions = graph.junctions_between("a", 50, "b", 50);
+        assert_eq!(junctions, vec![Junction { from: "a".to_string(), to: "b".to_string() }]);
+
+        // Both ends on the same side of the boundary: no junction crossed.
+        let junctions = graph.junctions_between("a", 10, "a", 20);
+        assert_eq!(junctions, Vec::new());
+
+        std::fs::remove_file(&gfa_path).ok();
+    }
+}
@@ -0,0 +1,352 @@
+//! A pairix-style 2D block index over a sorted, bgzipped `.pairs.gz` file,
+//! letting `query` seek directly to a region instead of scanning the whole
+//! file.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+use crate::bgzip::{split_virtual_offset, virtual_offset, BgzfBlockReader};
+use crate::pairs::PairRecord;
+
+/// Bin size (bp) used to partition each chromosome axis for the index key
+/// `(chrom1, chrom2, bin1)`.
+const INDEX_BIN_SIZE: u64 = 1_000_000;
+
+/// A parsed `chrom:start-end` region argument.
+#[derive(Debug, Clone)]
+pub struct Region {
+    pub chrom: String,
+    pub start: u64,
+    pub end: u64,
+}
+
+impl Region {
+    /// Parse a `chrom:start-end` CLI argument.
+    pub fn parse(spec: &str) -> Option<Region> {
+        let (chrom, range) = spec.split_once(':')?;
+        let (start, end) = range.split_once('-')?;
+        Some(Region { chrom: chrom.to_string(), start: start.parse().ok()?, end: end.parse().ok()? })
+    }
+
+    pub fn contains(&self, pos: u64) -> bool {
+        pos >= self.start && pos <= self.end
+    }
+}
+
+/// Build a `.px2`-style 2D index over a sorted, bgzipped `pairs_file`,
+/// recording for each `(chrom1, chrom2, bin1)` the virtual file offset of
+/// its first record.
+pub fn build_index(pairs_file: &Path, index_file: &Path) -> Result<(), Box<dyn Error>> {
+    let mut reader = BgzfBlockReader::open(pairs_file)?;
+    let mut block_offset = 0u64;
+    let mut index: BTreeMap<(String, String, u64), u64> = BTreeMap::new();
+    let mut leftover = String::new();
+    let mut leftover_voffset = 0u64;
+
+    loop {
+        let (block_bytes, next_offset) = reader.read_block(block_offset)?;
+        if block_bytes.is_empty() && next_offset == block_offset {
+            break;
+        }
+
+        let leftover_len = leftover.len();
+        let text = leftover + &String::from_utf8_lossy(&block_bytes);
+        leftover = String::new();
+
+        let mut line_start = 0usize;
+        for line in text.split_inclusive('\n') {
+            // A line starting in the carried-over text began in an earlier
+            // block, at the virtual offset remembered for it.
+            let line_voffset = if line_start >= leftover_len {
+                virtual_offset(block_offset, (line_start - leftover_len) as u16)
+            } else {
+                leftover_voffset
+            };
+            if !line.ends_with('\n') {
+                leftover = line.to_string();
+                leftover_voffset = line_voffset;
+                break;
+            }
+            let trimmed = line.trim_end();
+            if !trimmed.starts_with('#') {
+                if let Some(record) = PairRecord::parse(trimmed) {
+                    let bin1 = record.pos1 / INDEX_BIN_SIZE;
+                    let key = (record.chrom1.clone(), record.chrom2.clone(), bin1);
+                    index.entry(key).or_insert(line_voffset);
+                }
+            }
+            line_start += line.len();
+        }
+
+        if next_offset == block_offset {
+            break;
+        }
+        block_offset = next_offset;
+    }
+
+    write_index(index_file, &index)
+}
+
+fn write_index(index_file: &Path, index: &BTreeMap<(String, String, u64), u64>) -> Result<(), Box<dyn Error>> {
+    let mut writer = std::io::BufWriter::new(File::create(index_file)?);
+    for ((chrom1, chrom2, bin1), voffset) in index {
+        writeln!(writer, "{}\t{}\t{}\t{}", chrom1, chrom2, bin1, voffset)?;
+    }
+    Ok(())
+}
+
+fn load_index(index_file: &Path) -> Result<BTreeMap<(String, String, u64), u64>, Box<dyn Error>> {
+    let reader = BufReader::new(File::open(index_file)?);
+    let mut index = BTreeMap::new();
+    for line in reader.lines() {
+        let line = line?;
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 4 {
+            continue;
+        }
+        index.insert(
+            (fields[0].to_string(), fields[1].to_string(), fields[2].parse()?),
+            fields[3].parse()?,
+        );
+    }
+    Ok(index)
+}
+
+/// Seek via `index_file` and return only the pairs in `pairs_file`
+/// overlapping `region1` (and, when given, `region2`).
+///
+/// `#shape: upper triangle` means a trans pair is stored once, under
+/// whichever of `(chrom1, chrom2)` / `(chrom2, chrom1)` the writer happened
+/// to emit it as -- not necessarily the order the caller names the two
+/// regions in. If the literal order the caller gave comes back empty, retry
+/// with the two regions swapped before reporting no overlap, rather than
+/// silently missing records stored the other way around.
+pub fn query_pairs(
+    pairs_file: &Path,
+    index_file: &Path,
+    region1: &Region,
+    region2: Option<&Region>,
+) -> Result<Vec<PairRecord>, Box<dyn Error>> {
+    let index = load_index(index_file)?;
+
+    let results = query_pairs_in_order(&index, pairs_file, region1, region2)?;
+    if !results.is_empty() {
+        return Ok(results);
+    }
+    match region2 {
+        Some(region2) if region2.chrom != region1.chrom => {
+            query_pairs_in_order(&index, pairs_file, region2, Some(region1))
+        }
+        _ => Ok(results),
+    }
+}
+
+fn query_pairs_in_order(
+    index: &BTreeMap<(String, String, u64), u64>,
+    pairs_file: &Path,
+    region1: &Region,
+    region2: Option<&Region>,
+) -> Result<Vec<PairRecord>, Box<dyn Error>> {
+    let bin1_lo = region1.start / INDEX_BIN_SIZE;
+    let bin1_hi = region1.end / INDEX_BIN_SIZE;
+
+    // Records are sorted by (chrom1, chrom2, pos1, pos2), so every bin of a
+    // given (chrom1, chrom2) pair lies on one contiguous, pos1-ascending
+    // run: scanning forward from its *earliest* matching bin already walks
+    // through every later bin in range. Take just that one start offset
+    // per distinct chrom2 sharing region1.chrom, instead of one per bin,
+    // or the later bins' scans would re-walk (and duplicate) records the
+    // earliest one already covered.
+    let mut group_starts: BTreeMap<String, u64> = BTreeMap::new();
+    for ((chrom1, chrom2, bin1), voffset) in index {
+        if chrom1 != &region1.chrom || *bin1 < bin1_lo || *bin1 > bin1_hi {
+            continue;
+        }
+        if region2.map_or(false, |region2| chrom2 != &region2.chrom) {
+            continue;
+        }
+        group_starts.entry(chrom2.clone())
+            .and_modify(|existing| *existing = (*existing).min(*voffset))
+            .or_insert(*voffset);
+    }
+
+    let mut starts: Vec<(String, u64)> = group_starts.into_iter().collect();
+    starts.sort_unstable_by_key(|(_, voffset)| *voffset);
+
+    let mut results = Vec::new();
+    let mut reader = BgzfBlockReader::open(pairs_file)?;
+    for (chrom2, start_voffset) in starts {
+        let (start_block, start_uoffset) = split_virtual_offset(start_voffset);
+        if !scan_from(&mut reader, start_block, start_uoffset, region1, region2, &chrom2, &mut results)? {
+            break;
+        }
+    }
+
+    Ok(results)
+}
+
+/// Stream records from `start_block`/`start_uoffset` onward, collecting
+/// overlaps into `results`, stopping once either this `(chrom1, chrom2)`
+/// run ends (`chrom2` drifts off `expected_chrom2` or `pos1` passes
+/// `region1.end`) or `region1.chrom` itself is fully passed. Returns
+/// `false` only in the latter case, since `chrom1` is the outermost sort
+/// key and nothing later in the file can match region1 either; `true`
+/// otherwise, so the caller can move on to the next group's start offset.
+fn scan_from(
+    reader: &mut BgzfBlockReader,
+    start_block: u64,
+    start_uoffset: u16,
+    region1: &Region,
+    region2: Option<&Region>,
+    expected_chrom2: &str,
+    results: &mut Vec<PairRecord>,
+) -> Result<bool, Box<dyn Error>> {
+    let mut block_offset = start_block;
+    let mut carry = String::new();
+    let mut first_block = true;
+
+    loop {
+        let (block_bytes, next_offset) = reader.read_block(block_offset)?;
+        if block_bytes.is_empty() && next_offset == block_offset {
+            return Ok(true);
+        }
+
+        let text = if first_block {
+            String::from_utf8_lossy(&block_bytes[start_uoffset as usize..]).into_owned()
+        } else {
+            String::from_utf8_lossy(&block_bytes).into_owned()
+        };
+        first_block = false;
+
+        let combined = carry + &text;
+        carry = String::new();
+        for line in combined.split_inclusive('\n') {
+            if !line.ends_with('\n') {
+                carry = line.to_string();
+                continue;
+            }
+            let trimmed = line.trim_end();
+            if trimmed.starts_with('#') {
+                continue;
+            }
+            let record = match PairRecord::parse(trimmed) {
+                Some(record) => record,
+                None => continue,
+            };
+
+            if record.chrom1 != region1.chrom {
+                return Ok(false);
+            }
+            if record.chrom2 != expected_chrom2 || record.pos1 > region1.end {
+                return Ok(true);
+            }
+            if !region1.contains(record.pos1) {
+                continue;
+            }
+            if let Some(region2) = region2 {
+                if !region2.contains(record.pos2) {
+                    continue;
+                }
+            }
+            results.push(record);
+        }
+
+        if next_offset == block_offset {
+            return Ok(true);
+        }
+        block_offset = next_offset;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bgzip::BgzfWriter;
+    use std::io::{BufWriter, Write as _};
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("hic_convertor_index_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    /// Build a bgzipped `.pairs.gz` with 5 records on `chr1` spanning 3 of
+    /// the index's 1 Mb bins (bins 0, 1, 2), then index and query it,
+    /// regression-testing the bug where records overlapping more than one
+    /// bin came back duplicated once per bin.
+    #[test]
+    fn query_pairs_returns_each_record_once_across_multiple_bins() {
+        let pairs_path = temp_path("multi_bin.pairs.gz");
+        let index_path = temp_path("multi_bin.px2");
+
+        let records = [
+            "r1\tchr1\t100\tchr1\t200\t+\t+",
+            "r2\tchr1\t1_500_000\tchr1\t1_600_000\t+\t+",
+            "r3\tchr1\t1_700_000\tchr1\t1_800_000\t+\t+",
+            "r4\tchr1\t2_500_000\tchr1\t2_600_000\t+\t+",
+            "r5\tchr1\t2_900_000\tchr1\t2_950_000\t+\t+",
+        ].map(|line| line.replace('_', ""));
+
+        {
+            let file = File::create(&pairs_path).unwrap();
+            let mut writer = BgzfWriter::new(BufWriter::new(file));
+            writeln!(writer, "## pairs format v1.0").unwrap();
+            writeln!(writer, "#sorted: chr1-chr2-pos1-pos2").unwrap();
+            writeln!(writer, "#columns: readID chr1 pos1 chr2 pos2 strand1 strand2").unwrap();
+            for record in &records {
+                writeln!(writer, "{}", record).unwrap();
+            }
+            writer.finish().unwrap();
+        }
+
+        build_index(&pairs_path, &index_path).unwrap();
+
+        let region = Region { chrom: "chr1".to_string(), start: 0, end: 3_000_000 };
+        let results = query_pairs(&pairs_path, &index_path, &region, None).unwrap();
+
+        assert_eq!(results.len(), 5, "expected no duplicate records across bin boundaries: {:?}", results);
+        let mut read_ids: Vec<&str> = results.iter().map(|record| record.read_id.as_str()).collect();
+        read_ids.sort_unstable();
+        assert_eq!(read_ids, vec!["r1", "r2", "r3", "r4", "r5"]);
+
+        std::fs::remove_file(&pairs_path).ok();
+        std::fs::remove_file(&index_path).ok();
+    }
+
+    /// A trans pair stored as `chrA -> chrB` must still be found when the
+    /// caller names the two regions in the opposite order.
+    #[test]
+    fn query_pairs_finds_trans_records_regardless_of_caller_region_order() {
+        let pairs_path = temp_path("trans.pairs.gz");
+        let index_path = temp_path("trans.px2");
+
+        {
+            let file = File::create(&pairs_path).unwrap();
+            let mut writer = BgzfWriter::new(BufWriter::new(file));
+            writeln!(writer, "## pairs format v1.0").unwrap();
+            writeln!(writer, "#sorted: chr1-chr2-pos1-pos2").unwrap();
+            writeln!(writer, "#columns: readID chr1 pos1 chr2 pos2 strand1 strand2").unwrap();
+            writeln!(writer, "r1\tchrA\t100\tchrB\t200\t+\t+").unwrap();
+            writer.finish().unwrap();
+        }
+
+        build_index(&pairs_path, &index_path).unwrap();
+
+        let region_a = Region { chrom: "chrA".to_string(), start: 0, end: 1000 };
+        let region_b = Region { chrom: "chrB".to_string(), start: 0, end: 1000 };
+
+        // Literal order matching how the record is stored.
+        let forward = query_pairs(&pairs_path, &index_path, &region_a, Some(&region_b)).unwrap();
+        assert_eq!(forward.iter().map(|r| r.read_id.as_str()).collect::<Vec<_>>(), vec!["r1"]);
+
+        // Caller names the regions the other way around.
+        let swapped = query_pairs(&pairs_path, &index_path, &region_b, Some(&region_a)).unwrap();
+        assert_eq!(swapped.iter().map(|r| r.read_id.as_str()).collect::<Vec<_>>(), vec!["r1"]);
+
+        std::fs::remove_file(&pairs_path).ok();
+        std::fs::remove_file(&index_path).ok();
+    }
+}
@@ -0,0 +1,23 @@
+//! Core library behind the `convertor` CLI: conversion of Hi-C BAM
+//! alignments into the `.pairs` format, plus sorting, deduplication and
+//! QC utilities operating on that format.
+
+pub mod bgzip;
+pub mod convert;
+pub mod dedup;
+pub mod filter;
+pub mod format;
+pub mod graph;
+pub mod index;
+pub mod pairs;
+pub mod sort;
+pub mod stats;
+
+pub use convert::{convert_bam_to_pairs, full_pipeline};
+pub use dedup::{deduplicate_pairs, deduplicate_pairs_with_options, write_dedup_summary, DedupOptions, DedupSummary, DuplicateAction};
+pub use filter::{filter_pairs, ChromPattern, FilterCriteria, RegionMode};
+pub use format::OutFormat;
+pub use graph::{ContigGraph, Junction};
+pub use index::{build_index, query_pairs, Region};
+pub use sort::{sort_pairs, sort_pairs_with_chrom_sizes};
+pub use stats::{compute_stats, write_stats, PairsStats};
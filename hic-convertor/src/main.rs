@@ -5,7 +5,12 @@ use std::path::Path;
 use log::info;
 use fern;
 use clap::{Arg, App, SubCommand};
-use hic_convertor::{full_pipeline, convert_bam_to_pairs, deduplicate_pairs, sort_pairs};
+use regex::Regex;
+use hic_convertor::{
+    full_pipeline, convert_bam_to_pairs, deduplicate_pairs_with_options, sort_pairs_with_chrom_sizes,
+    compute_stats, write_stats, build_index, query_pairs, write_dedup_summary, filter_pairs,
+    DedupOptions, DuplicateAction, OutFormat, Region, ChromPattern, FilterCriteria, RegionMode,
+};
 
 fn setup_logging(verbosity: u64, log_file: &Path) -> Result<(), fern::InitError> {
     let mut base_config = fern::Dispatch::new();
@@ -93,6 +98,30 @@ fn main() -> Result<(), Box<dyn Error>> {
                         .required(false)
                         .help("Number of processes for sorting.")
                 )
+                .arg(
+                    Arg::with_name("out_format")
+                        .long("out-format")
+                        .value_name("FORMAT")
+                        .takes_value(true)
+                        .required(false)
+                        .possible_values(&["pairs", "matrix"])
+                        .help("Output format: 4DN pairs (default) or a binned contact matrix.")
+                )
+                .arg(
+                    Arg::with_name("bin_size")
+                        .long("bin-size")
+                        .value_name("BP")
+                        .takes_value(true)
+                        .required(false)
+                        .help("Bin size in bp, required when --out-format matrix is used.")
+                )
+                .arg(
+                    Arg::with_name("bgzip")
+                        .long("bgzip")
+                        .takes_value(false)
+                        .required(false)
+                        .help("Additionally write the deduplicated pairs block-gzipped.")
+                )
         )
         .subcommand(
             SubCommand::with_name("convert")
@@ -124,10 +153,34 @@ fn main() -> Result<(), Box<dyn Error>> {
                         .required(false)
                         .help("Path to graph in gfa format.")
                 )
+                .arg(
+                    Arg::with_name("out_format")
+                        .long("out-format")
+                        .value_name("FORMAT")
+                        .takes_value(true)
+                        .required(false)
+                        .possible_values(&["pairs", "matrix"])
+                        .help("Output format: 4DN pairs (default) or a binned contact matrix.")
+                )
+                .arg(
+                    Arg::with_name("bin_size")
+                        .long("bin-size")
+                        .value_name("BP")
+                        .takes_value(true)
+                        .required(false)
+                        .help("Bin size in bp, required when --out-format matrix is used.")
+                )
+                .arg(
+                    Arg::with_name("bgzip")
+                        .long("bgzip")
+                        .takes_value(false)
+                        .required(false)
+                        .help("Write block-gzipped (.pairs.gz) output.")
+                )
         )
         .subcommand(
             SubCommand::with_name("sort")
-                .about("Sort pairs file using sort command (see man sort).")
+                .about("Sort pairs file with a native parallel external merge sort.")
                 .arg(
                     Arg::with_name("in_pairs")
                         .short("p")
@@ -146,6 +199,14 @@ fn main() -> Result<(), Box<dyn Error>> {
                         .required(true)
                         .help("Output file with sorted pairs.")
                 )
+                .arg(
+                    Arg::with_name("chrom_sizes")
+                        .long("chrom-sizes")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .required(false)
+                        .help("Chrom-sizes file giving chromosome sort order, overriding the #chromosomes header.")
+                )
                 .arg(
                     Arg::with_name("nproc")
                         .short("t")
@@ -158,7 +219,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         )
         .subcommand(
             SubCommand::with_name("dedup")
-                .about("Remove duplicated Hi-C reads from file.")
+                .about("Remove or mark duplicated Hi-C reads from a sorted pairs file.")
                 .arg(
                     Arg::with_name("in_pairs")
                         .short("p")
@@ -177,6 +238,206 @@ fn main() -> Result<(), Box<dyn Error>> {
                         .required(true)
                         .help("Output file with sorted pairs.")
                 )
+                .arg(
+                    Arg::with_name("max_mismatch")
+                        .long("max-mismatch")
+                        .value_name("BP")
+                        .takes_value(true)
+                        .required(false)
+                        .help("Positional tolerance in bp for PCR/optical duplicate detection (default 0, exact match).")
+                )
+                .arg(
+                    Arg::with_name("mark")
+                        .long("mark")
+                        .takes_value(false)
+                        .required(false)
+                        .help("Keep duplicates but flag them with a trailing column, instead of dropping them.")
+                )
+                .arg(
+                    Arg::with_name("require_same_strand")
+                        .long("require-same-strand")
+                        .takes_value(false)
+                        .required(false)
+                        .help("Only treat candidates as duplicates when both strands also match.")
+                )
+                .arg(
+                    Arg::with_name("summary")
+                        .long("summary")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .required(false)
+                        .help("File where the duplication summary (counts, estimated complexity) is saved.")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("stats")
+                .about("Compute Hi-C QC metrics from a pairs file.")
+                .arg(
+                    Arg::with_name("in_pairs")
+                        .short("p")
+                        .long("in_pairs")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Input file with pairs.")
+                )
+                .arg(
+                    Arg::with_name("out")
+                        .short("o")
+                        .long("out")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .required(true)
+                        .help("File where the report will be saved.")
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .takes_value(false)
+                        .required(false)
+                        .help("Emit a machine-readable JSON report instead of TSV.")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("index")
+                .about("Build a pairix-style 2D index over a sorted, bgzipped pairs file.")
+                .arg(
+                    Arg::with_name("in_pairs")
+                        .short("p")
+                        .long("in_pairs")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Sorted, bgzipped (.pairs.gz) input file.")
+                )
+                .arg(
+                    Arg::with_name("out_index")
+                        .short("o")
+                        .long("out_index")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .required(true)
+                        .help("File where the .px2-style index will be saved.")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("query")
+                .about("Look up pairs overlapping one or two regions via a 2D index.")
+                .arg(
+                    Arg::with_name("in_pairs")
+                        .short("p")
+                        .long("in_pairs")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Sorted, bgzipped (.pairs.gz) input file.")
+                )
+                .arg(
+                    Arg::with_name("index")
+                        .short("x")
+                        .long("index")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Index file produced by the index subcommand.")
+                )
+                .arg(
+                    Arg::with_name("region1")
+                        .value_name("chr1:start-end")
+                        .required(true)
+                        .help("First region, e.g. chr1:1000-2000.")
+                )
+                .arg(
+                    Arg::with_name("region2")
+                        .value_name("chr2:start-end")
+                        .required(false)
+                        .help("Optional second region to also constrain the other end.")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("filter")
+                .about("Select pairs matching region/chromosome/distance criteria (AND semantics).")
+                .arg(
+                    Arg::with_name("in_pairs")
+                        .short("p")
+                        .long("in_pairs")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Input file with pairs.")
+                )
+                .arg(
+                    Arg::with_name("out_pairs")
+                        .short("o")
+                        .long("out_pairs")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Output file with the selected pairs.")
+                )
+                .arg(
+                    Arg::with_name("region")
+                        .long("region")
+                        .value_name("chrom:start-end")
+                        .takes_value(true)
+                        .required(false)
+                        .help("Keep pairs where an end overlaps this region (see --both).")
+                )
+                .arg(
+                    Arg::with_name("both")
+                        .long("both")
+                        .takes_value(false)
+                        .required(false)
+                        .help("Require both ends to overlap --region, instead of either.")
+                )
+                .arg(
+                    Arg::with_name("chrom")
+                        .long("chrom")
+                        .value_name("GLOB")
+                        .takes_value(true)
+                        .required(false)
+                        .help("Keep pairs with either chromosome matching this glob (e.g. 'chr1*').")
+                )
+                .arg(
+                    Arg::with_name("chrom_re")
+                        .long("chrom-re")
+                        .value_name("REGEX")
+                        .takes_value(true)
+                        .required(false)
+                        .conflicts_with("chrom")
+                        .help("Keep pairs with either chromosome matching this regular expression.")
+                )
+                .arg(
+                    Arg::with_name("cis_only")
+                        .long("cis-only")
+                        .takes_value(false)
+                        .required(false)
+                        .conflicts_with("trans_only")
+                        .help("Keep only cis pairs (chrom1 == chrom2).")
+                )
+                .arg(
+                    Arg::with_name("trans_only")
+                        .long("trans-only")
+                        .takes_value(false)
+                        .required(false)
+                        .help("Keep only trans pairs (chrom1 != chrom2).")
+                )
+                .arg(
+                    Arg::with_name("min_distance")
+                        .long("min-distance")
+                        .value_name("BP")
+                        .takes_value(true)
+                        .required(false)
+                        .help("Minimum cis contact distance to keep.")
+                )
+                .arg(
+                    Arg::with_name("max_distance")
+                        .long("max-distance")
+                        .value_name("BP")
+                        .takes_value(true)
+                        .required(false)
+                        .help("Maximum cis contact distance to keep.")
+                )
         )
         .get_matches();
 
@@ -186,36 +447,107 @@ fn main() -> Result<(), Box<dyn Error>> {
             let bam_file = all_matches.value_of("bam").unwrap();
             let out_dir = all_matches.value_of("out").unwrap();
             let nproc: u8 = all_matches.value_of("nproc").unwrap_or("4").parse().unwrap();
+            let out_format = OutFormat::parse(all_matches.value_of("out_format").unwrap_or("pairs"))
+                .expect("invalid --out-format value");
+            let bin_size: Option<u64> = all_matches.value_of("bin_size").map(|v| v.parse().unwrap());
+            let bgzip = all_matches.is_present("bgzip");
+            let graph = all_matches.value_of("graph").map(Path::new);
             info!("all with {} {} {}", bam_file, out_dir, nproc);
-            match all_matches.value_of("graph") {
-                None =>  full_pipeline(Path::new(bam_file), None, Path::new(out_dir), nproc)?,
-                Some(_) => full_pipeline(Path::new(bam_file), None, Path::new(out_dir), nproc)?,
-            }
+            full_pipeline(Path::new(bam_file), graph, Path::new(out_dir), nproc, out_format, bin_size, bgzip)?;
         },
         ("convert", Some(convert_matches)) => {
             setup_logging(1, "convert.log".as_ref()).expect("failed to initialize logging.");
             let bam_file = convert_matches.value_of("bam").unwrap();
             let pairs_file = convert_matches.value_of("pairs").unwrap();
+            let out_format = OutFormat::parse(convert_matches.value_of("out_format").unwrap_or("pairs"))
+                .expect("invalid --out-format value");
+            let bin_size: Option<u64> = convert_matches.value_of("bin_size").map(|v| v.parse().unwrap());
+            let bgzip = convert_matches.is_present("bgzip");
+            let graph = convert_matches.value_of("graph").map(Path::new);
             info!("convert with {} {}", bam_file, pairs_file);
-            match convert_matches.value_of("graph") {
-                None =>  convert_bam_to_pairs(Path::new(bam_file), None, Path::new(pairs_file), Path::new("stats.txt"))?,
-                Some(_) => convert_bam_to_pairs(Path::new(bam_file), None, Path::new(pairs_file), Path::new("stats.txt"))?,
-            }
+            convert_bam_to_pairs(Path::new(bam_file), graph, Path::new(pairs_file), Path::new("stats.txt"), out_format, bin_size, bgzip)?;
         },
         ("sort", Some(sort_matches)) => {
             setup_logging(1, "convert.log".as_ref()).expect("failed to initialize logging.");
             let in_file = sort_matches.value_of("in_pairs").unwrap();
             let out_file = sort_matches.value_of("out_pairs").unwrap();
             let nproc: u8 = sort_matches.value_of("nproc").unwrap_or("4").parse().unwrap();
+            let chrom_sizes = sort_matches.value_of("chrom_sizes").map(Path::new);
             info!("sort with {} {} {}", in_file, out_file, nproc);
-            sort_pairs(Path::new(in_file), Path::new(out_file), Option::from(Path::new("tmp_sort_dir")), nproc)?;
+            sort_pairs_with_chrom_sizes(Path::new(in_file), Path::new(out_file), Option::from(Path::new("tmp_sort_dir")), nproc, chrom_sizes)?;
         },
         ("dedup", Some(dedup_matches)) => {
             setup_logging(1, "convert.log".as_ref()).expect("failed to initialize logging.");
             let in_file = dedup_matches.value_of("in_pairs").unwrap();
             let out_file = dedup_matches.value_of("out_pairs").unwrap();
-            info!("sort with {} {}", in_file, out_file);
-            deduplicate_pairs(Path::new(in_file), Path::new(out_file));
+            let options = DedupOptions {
+                max_mismatch: dedup_matches.value_of("max_mismatch").unwrap_or("0").parse().unwrap(),
+                action: if dedup_matches.is_present("mark") { DuplicateAction::Mark } else { DuplicateAction::Drop },
+                require_same_strand: dedup_matches.is_present("require_same_strand"),
+            };
+            info!("dedup with {} {} max_mismatch={}", in_file, out_file, options.max_mismatch);
+            let summary = deduplicate_pairs_with_options(Path::new(in_file), Path::new(out_file), options);
+            if let Some(summary_file) = dedup_matches.value_of("summary") {
+                write_dedup_summary(&summary, Path::new(summary_file))?;
+            }
+        }
+        ("stats", Some(stats_matches)) => {
+            setup_logging(1, "convert.log".as_ref()).expect("failed to initialize logging.");
+            let in_file = stats_matches.value_of("in_pairs").unwrap();
+            let out_file = stats_matches.value_of("out").unwrap();
+            let json = stats_matches.is_present("json");
+            info!("stats with {} {} json={}", in_file, out_file, json);
+            let stats = compute_stats(Path::new(in_file))?;
+            write_stats(&stats, Path::new(out_file), json)?;
+        }
+        ("index", Some(index_matches)) => {
+            setup_logging(1, "convert.log".as_ref()).expect("failed to initialize logging.");
+            let in_file = index_matches.value_of("in_pairs").unwrap();
+            let out_index = index_matches.value_of("out_index").unwrap();
+            info!("index with {} {}", in_file, out_index);
+            build_index(Path::new(in_file), Path::new(out_index))?;
+        }
+        ("query", Some(query_matches)) => {
+            setup_logging(1, "convert.log".as_ref()).expect("failed to initialize logging.");
+            let in_file = query_matches.value_of("in_pairs").unwrap();
+            let index_file = query_matches.value_of("index").unwrap();
+            let region1 = Region::parse(query_matches.value_of("region1").unwrap())
+                .expect("invalid region1, expected chrom:start-end");
+            let region2 = query_matches.value_of("region2")
+                .map(|spec| Region::parse(spec).expect("invalid region2, expected chrom:start-end"));
+            info!("query with {} {}", in_file, index_file);
+            let records = query_pairs(Path::new(in_file), Path::new(index_file), &region1, region2.as_ref())?;
+            for record in records {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    record.read_id, record.chrom1, record.pos1, record.chrom2, record.pos2,
+                    record.strand1, record.strand2,
+                );
+            }
+        }
+        ("filter", Some(filter_matches)) => {
+            setup_logging(1, "convert.log".as_ref()).expect("failed to initialize logging.");
+            let in_file = filter_matches.value_of("in_pairs").unwrap();
+            let out_file = filter_matches.value_of("out_pairs").unwrap();
+            let region = filter_matches.value_of("region")
+                .map(|spec| Region::parse(spec).expect("invalid --region, expected chrom:start-end"));
+            let region_mode = if filter_matches.is_present("both") { RegionMode::Both } else { RegionMode::Either };
+            let chrom_pattern = match (filter_matches.value_of("chrom"), filter_matches.value_of("chrom_re")) {
+                (Some(glob), _) => Some(ChromPattern::Glob(glob.to_string())),
+                (None, Some(re)) => Some(ChromPattern::Regex(Regex::new(re).expect("invalid --chrom-re regex"))),
+                (None, None) => None,
+            };
+            let criteria = FilterCriteria {
+                region,
+                region_mode,
+                chrom_pattern,
+                cis_only: filter_matches.is_present("cis_only"),
+                trans_only: filter_matches.is_present("trans_only"),
+                min_distance: filter_matches.value_of("min_distance").map(|v| v.parse().unwrap()),
+                max_distance: filter_matches.value_of("max_distance").map(|v| v.parse().unwrap()),
+            };
+            info!("filter with {} {}", in_file, out_file);
+            filter_pairs(Path::new(in_file), Path::new(out_file), &criteria)?;
         }
         ("", None) => println!("None subcommand was used. See help for available one."),
         _ => unreachable!(),
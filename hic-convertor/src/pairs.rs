@@ -0,0 +1,89 @@
+//! Shared record type and header handling for the `.pairs` text format.
+
+use std::io::{BufRead, Result};
+
+/// Index of the optional duplicate-flag column, when present.
+const DUP_COLUMN_INDEX: usize = 7;
+
+/// A single Hi-C contact: one record line of a `.pairs` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PairRecord {
+    pub read_id: String,
+    pub chrom1: String,
+    pub pos1: u64,
+    pub chrom2: String,
+    pub pos2: u64,
+    pub strand1: char,
+    pub strand2: char,
+    pub is_duplicate: bool,
+}
+
+impl PairRecord {
+    /// Parse a tab-separated `.pairs` record line. Returns `None` for blank
+    /// or malformed lines so callers can skip them without failing the run.
+    pub fn parse(line: &str) -> Option<PairRecord> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 7 {
+            return None;
+        }
+        let is_duplicate = fields.get(DUP_COLUMN_INDEX)
+            .map(|flag| matches!(*flag, "1" | "true" | "dup" | "DD"))
+            .unwrap_or(false);
+
+        Some(PairRecord {
+            read_id: fields[0].to_string(),
+            chrom1: fields[1].to_string(),
+            pos1: fields[2].parse().ok()?,
+            chrom2: fields[3].to_string(),
+            pos2: fields[4].parse().ok()?,
+            strand1: fields[5].chars().next()?,
+            strand2: fields[6].chars().next()?,
+            is_duplicate,
+        })
+    }
+
+    /// `true` when both ends of the pair land on the same chromosome.
+    pub fn is_cis(&self) -> bool {
+        self.chrom1 == self.chrom2
+    }
+
+    /// Linear separation between the two ends of a cis pair, or `None` for
+    /// trans pairs where the notion is meaningless.
+    pub fn contact_distance(&self) -> Option<u64> {
+        if self.is_cis() {
+            Some(self.pos1.abs_diff(self.pos2))
+        } else {
+            None
+        }
+    }
+
+    /// FF/RR/FR/RF orientation label derived from the two strand columns.
+    pub fn orientation(&self) -> &'static str {
+        match (self.strand1, self.strand2) {
+            ('+', '+') => "FF",
+            ('-', '-') => "RR",
+            ('+', '-') => "FR",
+            ('-', '+') => "RF",
+            _ => "??",
+        }
+    }
+}
+
+/// Consume the leading `#`-prefixed header block of a `.pairs` stream,
+/// leaving `reader` positioned at the first record line.
+pub fn read_pairs_header<R: BufRead>(reader: &mut R) -> Result<Vec<String>> {
+    let mut header = Vec::new();
+    loop {
+        let starts_with_hash = {
+            let buf = reader.fill_buf()?;
+            buf.first() == Some(&b'#')
+        };
+        if !starts_with_hash {
+            break;
+        }
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        header.push(line.trim_end().to_string());
+    }
+    Ok(header)
+}
@@ -0,0 +1,356 @@
+//! Native parallel external merge sort for `.pairs` files, replacing a
+//! shell-out to the system `sort` binary.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+use log::info;
+
+use crate::pairs::{read_pairs_header, PairRecord};
+
+/// Target number of records per spilled chunk. Kept small enough that a
+/// chunk comfortably fits in memory for in-place sorting.
+const CHUNK_SIZE: usize = 1_000_000;
+
+type SortKey = (usize, String, usize, String, u64, u64);
+
+/// Chromosome ordering used for the sort key's chromosome component, taken
+/// from a `#chromosomes` header block or, when given, an explicit
+/// chrom-sizes file rather than lexicographic order. Chromosomes absent
+/// from the source in use sort last, lexicographically among themselves.
+struct ChromOrder {
+    rank: HashMap<String, usize>,
+}
+
+impl ChromOrder {
+    fn from_names<I: IntoIterator<Item = String>>(names: I) -> ChromOrder {
+        let mut rank = HashMap::new();
+        for (idx, name) in names.into_iter().enumerate() {
+            rank.entry(name).or_insert(idx);
+        }
+        ChromOrder { rank }
+    }
+
+    /// Read `chrom  length` lines from a chrom-sizes file, in file order.
+    fn from_chrom_sizes(path: &Path) -> Result<ChromOrder, Box<dyn Error>> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut names = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if let Some(chrom) = line.split_whitespace().next() {
+                names.push(chrom.to_string());
+            }
+        }
+        Ok(ChromOrder::from_names(names))
+    }
+
+    /// Read chromosome order from a `.pairs` header's `#chromosomes` lines.
+    fn from_header(header: &[String]) -> ChromOrder {
+        let names = header.iter().filter_map(|line| {
+            line.strip_prefix("#chromosomes:")
+                .and_then(|rest| rest.split_whitespace().next())
+                .map(|name| name.to_string())
+        });
+        ChromOrder::from_names(names)
+    }
+
+    fn rank_of(&self, chrom: &str) -> usize {
+        self.rank.get(chrom).copied().unwrap_or(usize::MAX)
+    }
+
+    fn key(&self, record: &PairRecord) -> SortKey {
+        (
+            self.rank_of(&record.chrom1), record.chrom1.clone(),
+            self.rank_of(&record.chrom2), record.chrom2.clone(),
+            record.pos1, record.pos2,
+        )
+    }
+}
+
+/// Sort `in_pairs` by `(chrom1, chrom2, pos1, pos2)` into `out_pairs` — the
+/// 4DN/pairix block-sort order, matching the `#sorted` field this module
+/// (and the 2D index built over its output) both rely on — ordering
+/// chromosomes as declared by the input's `#chromosomes` header rather
+/// than lexicographically.
+pub fn sort_pairs(
+    in_pairs: &Path,
+    out_pairs: &Path,
+    tmp_dir: Option<&Path>,
+    nproc: u8,
+) -> Result<(), Box<dyn Error>> {
+    sort_pairs_with_chrom_sizes(in_pairs, out_pairs, tmp_dir, nproc, None)
+}
+
+/// As [`sort_pairs`], but take chromosome order from `chrom_sizes` (a
+/// `chrom  length` file) instead of the input's `#chromosomes` header.
+///
+/// Splits the record body into chunks of up to [`CHUNK_SIZE`] lines, sorts
+/// each chunk on a pool of `nproc` worker threads and spills it to
+/// `tmp_dir`, then k-way merges the sorted chunks with a binary min-heap.
+/// The `.pairs` header is preserved and its `#sorted` field updated.
+pub fn sort_pairs_with_chrom_sizes(
+    in_pairs: &Path,
+    out_pairs: &Path,
+    tmp_dir: Option<&Path>,
+    nproc: u8,
+    chrom_sizes: Option<&Path>,
+) -> Result<(), Box<dyn Error>> {
+    info!("sorting {} -> {}", in_pairs.display(), out_pairs.display());
+
+    let tmp_dir_buf = tmp_dir.map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from("tmp_sort_dir"));
+    std::fs::create_dir_all(&tmp_dir_buf)?;
+
+    let mut reader = BufReader::new(crate::bgzip::open_transparent(in_pairs)?);
+    let header = read_pairs_header(&mut reader)?;
+
+    let order = match chrom_sizes {
+        Some(path) => ChromOrder::from_chrom_sizes(path)?,
+        None => ChromOrder::from_header(&header),
+    };
+
+    let chunk_paths = spill_sorted_chunks(&mut reader, &tmp_dir_buf, nproc, &order)?;
+    merge_sorted_chunks(&chunk_paths, out_pairs, &header, &order)?;
+
+    for path in &chunk_paths {
+        let _ = std::fs::remove_file(path);
+    }
+
+    Ok(())
+}
+
+/// Split the record body into chunks of up to [`CHUNK_SIZE`] lines, sort
+/// each chunk by `order`'s key on a pool of `nproc` worker threads, and
+/// spill the sorted chunks to `tmp_dir`.
+///
+/// Chunks are handed to the worker pool through a channel bounded at
+/// `nproc` entries as soon as each one is read, instead of reading the
+/// whole input into memory first: once that many chunks are queued, the
+/// reader blocks until a worker drains one. Resident memory therefore
+/// stays within about `(2 * nproc + 1) * CHUNK_SIZE` records (chunks
+/// queued, chunks being sorted, and the one being read) regardless of
+/// input size,
+/// which is the entire point of an *external* sort.
+fn spill_sorted_chunks<R: BufRead>(
+    reader: &mut R,
+    tmp_dir: &Path,
+    nproc: u8,
+    order: &ChromOrder,
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let nworkers = nproc.max(1) as usize;
+    let (sender, receiver) = mpsc::sync_channel::<(usize, Vec<String>)>(nworkers);
+    let receiver = Mutex::new(receiver);
+    let errors: Mutex<Vec<io::Error>> = Mutex::new(Vec::new());
+    let chunk_paths: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+    let read_result: io::Result<()> = thread::scope(|scope| {
+        for _ in 0..nworkers {
+            scope.spawn(|| loop {
+                let next = receiver.lock().unwrap().recv();
+                let (idx, lines) = match next {
+                    Ok(item) => item,
+                    Err(_) => break,
+                };
+                let sorted = sort_chunk(lines, order);
+                let path = tmp_dir.join(format!("chunk-{:05}.pairs", idx));
+                match write_chunk(&path, &sorted) {
+                    Ok(()) => chunk_paths.lock().unwrap().push(path),
+                    Err(err) => errors.lock().unwrap().push(err),
+                }
+            });
+        }
+
+        let mut current = Vec::with_capacity(CHUNK_SIZE);
+        let mut line = String::new();
+        let mut idx = 0usize;
+        let result = loop {
+            line.clear();
+            match reader.read_line(&mut line) {
+                Ok(0) => break Ok(()),
+                Ok(_) => {}
+                Err(err) => break Err(err),
+            }
+            current.push(line.trim_end().to_string());
+            if current.len() >= CHUNK_SIZE {
+                let chunk = std::mem::replace(&mut current, Vec::with_capacity(CHUNK_SIZE));
+                if sender.send((idx, chunk)).is_err() {
+                    break Ok(());
+                }
+                idx += 1;
+            }
+        };
+        if !current.is_empty() {
+            let _ = sender.send((idx, current));
+        }
+        drop(sender);
+        result
+    });
+
+    read_result?;
+    if let Some(err) = errors.into_inner().unwrap().into_iter().next() {
+        return Err(Box::new(err));
+    }
+
+    let mut chunk_paths = chunk_paths.into_inner().unwrap();
+    chunk_paths.sort();
+    Ok(chunk_paths)
+}
+
+fn sort_chunk(lines: Vec<String>, order: &ChromOrder) -> Vec<String> {
+    let mut keyed: Vec<(SortKey, String)> = lines.into_iter()
+        .filter_map(|line| {
+            let record = PairRecord::parse(&line)?;
+            Some((order.key(&record), line))
+        })
+        .collect();
+    keyed.sort_by(|a, b| a.0.cmp(&b.0));
+    keyed.into_iter().map(|(_, line)| line).collect()
+}
+
+fn write_chunk(path: &Path, lines: &[String]) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    for line in lines {
+        writeln!(writer, "{}", line)?;
+    }
+    Ok(())
+}
+
+/// One candidate line in the k-way merge's min-heap, ordered by its sort
+/// key in reverse so that `BinaryHeap` (a max-heap) yields the smallest key
+/// first.
+struct HeapEntry {
+    key: SortKey,
+    line: String,
+    source: usize,
+}
+
+impl PartialEq for HeapEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for HeapEntry {}
+
+impl PartialOrd for HeapEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for HeapEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.key.cmp(&self.key)
+    }
+}
+
+fn next_entry<R: BufRead>(reader: &mut R, source: usize, order: &ChromOrder) -> Result<Option<HeapEntry>, Box<dyn Error>> {
+    let mut line = String::new();
+    let bytes_read = reader.read_line(&mut line)?;
+    if bytes_read == 0 {
+        return Ok(None);
+    }
+    let trimmed = line.trim_end().to_string();
+    let record = PairRecord::parse(&trimmed)
+        .ok_or_else(|| format!("malformed pairs record: {}", trimmed))?;
+    Ok(Some(HeapEntry { key: order.key(&record), line: trimmed, source }))
+}
+
+/// K-way merge the per-chunk sorted files into `out_pairs`, writing back
+/// `header` with `#sorted` updated to reflect the new order.
+fn merge_sorted_chunks(
+    chunk_paths: &[PathBuf],
+    out_pairs: &Path,
+    header: &[String],
+    order: &ChromOrder,
+) -> Result<(), Box<dyn Error>> {
+    let mut readers: Vec<BufReader<File>> = chunk_paths.iter()
+        .map(|path| Ok::<_, io::Error>(BufReader::new(File::open(path)?)))
+        .collect::<Result<_, _>>()?;
+
+    let mut writer = BufWriter::new(File::create(out_pairs)?);
+    let mut wrote_sorted_field = false;
+    for line in header {
+        if line.starts_with("#sorted") {
+            writeln!(writer, "#sorted: chr1-chr2-pos1-pos2")?;
+            wrote_sorted_field = true;
+        } else {
+            writeln!(writer, "{}", line)?;
+        }
+    }
+    if !wrote_sorted_field {
+        writeln!(writer, "#sorted: chr1-chr2-pos1-pos2")?;
+    }
+
+    let mut heap = BinaryHeap::new();
+    for (source, reader) in readers.iter_mut().enumerate() {
+        if let Some(entry) = next_entry(reader, source, order)? {
+            heap.push(entry);
+        }
+    }
+
+    while let Some(HeapEntry { line, source, .. }) = heap.pop() {
+        writeln!(writer, "{}", line)?;
+        if let Some(entry) = next_entry(&mut readers[source], source, order)? {
+            heap.push(entry);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hic_convertor_sort_test_{}_{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn sorts_by_chrom_order_then_position_and_updates_header() {
+        let dir = temp_dir("round_trip");
+        let in_path = dir.join("unsorted.pairs");
+        let out_path = dir.join("sorted.pairs");
+
+        std::fs::write(&in_path, concat!(
+            "## pairs format v1.0\n",
+            "#sorted: none\n",
+            "#chromosomes: chr2 2000\n",
+            "#chromosomes: chr1 1000\n",
+            "#columns: readID chr1 pos1 chr2 pos2 strand1 strand2\n",
+            "r1\tchr1\t500\tchr2\t10\t+\t+\n",
+            "r2\tchr2\t5\tchr2\t50\t+\t+\n",
+            "r3\tchr1\t100\tchr1\t200\t+\t+\n",
+            "r4\tchr2\t5\tchr1\t10\t+\t+\n",
+        )).unwrap();
+
+        sort_pairs(&in_path, &out_path, Some(&dir.join("tmp")), 2).unwrap();
+
+        let sorted = std::fs::read_to_string(&out_path).unwrap();
+        let mut lines = sorted.lines();
+        assert_eq!(lines.next(), Some("## pairs format v1.0"));
+        assert_eq!(lines.next(), Some("#sorted: chr1-chr2-pos1-pos2"));
+        let records: Vec<&str> = lines.filter(|line| !line.starts_with('#')).collect();
+
+        // chrom order is chr2, chr1 (as declared by #chromosomes), so
+        // chrom1 == chr2 records sort before chrom1 == chr1 ones, chrom2
+        // follows the same order, and within a (chrom1, chrom2) pair
+        // records are pos1-ascending.
+        assert_eq!(records, vec![
+            "r2\tchr2\t5\tchr2\t50\t+\t+",
+            "r4\tchr2\t5\tchr1\t10\t+\t+",
+            "r1\tchr1\t500\tchr2\t10\t+\t+",
+            "r3\tchr1\t100\tchr1\t200\t+\t+",
+        ]);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
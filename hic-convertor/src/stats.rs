@@ -0,0 +1,171 @@
+//! QC statistics over a Hi-C `.pairs` file.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+use log::info;
+
+use crate::pairs::{read_pairs_header, PairRecord};
+
+/// Edges (in bp) of the log-spaced cis contact-distance bins used by
+/// [`compute_stats`]; each bin spans `[edge, next edge)`. The last bin
+/// collects everything at or beyond 1 Mb.
+const DISTANCE_BIN_EDGES: [u64; 6] = [0, 1_000, 10_000, 100_000, 1_000_000, u64::MAX];
+
+fn format_bp(value: u64) -> String {
+    match value {
+        0 => "0".to_string(),
+        v if v >= 1_000_000 => format!("{}Mb", v / 1_000_000),
+        v if v >= 1_000 => format!("{}kb", v / 1_000),
+        v => v.to_string(),
+    }
+}
+
+fn bin_label(lower: u64, upper: u64) -> String {
+    if upper == u64::MAX {
+        format!(">={}", format_bp(lower))
+    } else {
+        format!("{}-{}", format_bp(lower), format_bp(upper))
+    }
+}
+
+/// Per-file Hi-C QC summary produced by [`compute_stats`].
+#[derive(Debug, Default)]
+pub struct PairsStats {
+    pub total_pairs: u64,
+    pub cis_pairs: u64,
+    pub trans_pairs: u64,
+    pub duplicate_pairs: u64,
+    pub orientation_counts: BTreeMap<&'static str, u64>,
+    pub distance_bins: Vec<(String, u64)>,
+}
+
+impl PairsStats {
+    /// Ratio of cis to trans pairs; `+inf` when there are no trans pairs.
+    pub fn cis_trans_ratio(&self) -> f64 {
+        if self.trans_pairs == 0 {
+            f64::INFINITY
+        } else {
+            self.cis_pairs as f64 / self.trans_pairs as f64
+        }
+    }
+
+    /// Fraction of all pairs flagged as duplicates.
+    pub fn duplicate_fraction(&self) -> f64 {
+        if self.total_pairs == 0 {
+            0.0
+        } else {
+            self.duplicate_pairs as f64 / self.total_pairs as f64
+        }
+    }
+
+    /// Render the report as a two-column `metric\tvalue` TSV.
+    pub fn to_tsv(&self) -> String {
+        let mut out = String::new();
+        out.push_str(&format!("total_pairs\t{}\n", self.total_pairs));
+        out.push_str(&format!("cis_pairs\t{}\n", self.cis_pairs));
+        out.push_str(&format!("trans_pairs\t{}\n", self.trans_pairs));
+        out.push_str(&format!("cis_trans_ratio\t{:.4}\n", self.cis_trans_ratio()));
+        out.push_str(&format!("duplicate_pairs\t{}\n", self.duplicate_pairs));
+        out.push_str(&format!("duplicate_fraction\t{:.4}\n", self.duplicate_fraction()));
+        for (orientation, count) in &self.orientation_counts {
+            out.push_str(&format!("orientation_{}\t{}\n", orientation, count));
+        }
+        for (label, count) in &self.distance_bins {
+            out.push_str(&format!("cis_distance_{}\t{}\n", label, count));
+        }
+        out
+    }
+
+    /// Render the report as a single-line JSON object.
+    pub fn to_json(&self) -> String {
+        let orientations: Vec<String> = self.orientation_counts.iter()
+            .map(|(label, count)| format!("\"{}\":{}", label, count))
+            .collect();
+        let bins: Vec<String> = self.distance_bins.iter()
+            .map(|(label, count)| format!("{{\"bin\":\"{}\",\"count\":{}}}", label, count))
+            .collect();
+        // JSON has no infinity, so a ratio without trans pairs is `null`.
+        let ratio = self.cis_trans_ratio();
+        let ratio = if ratio.is_finite() { format!("{:.4}", ratio) } else { "null".to_string() };
+        format!(
+            "{{\"total_pairs\":{},\"cis_pairs\":{},\"trans_pairs\":{},\"cis_trans_ratio\":{},\
+             \"duplicate_pairs\":{},\"duplicate_fraction\":{:.4},\"orientation\":{{{}}},\"distance_bins\":[{}]}}",
+            self.total_pairs,
+            self.cis_pairs,
+            self.trans_pairs,
+            ratio,
+            self.duplicate_pairs,
+            self.duplicate_fraction(),
+            orientations.join(","),
+            bins.join(","),
+        )
+    }
+}
+
+/// Stream `pairs_file` once and compute QC metrics: total/cis/trans counts,
+/// a pair-orientation breakdown, a log-binned cis contact-distance
+/// histogram, and the fraction flagged as duplicates.
+pub fn compute_stats(pairs_file: &Path) -> Result<PairsStats, Box<dyn Error>> {
+    info!("computing stats for {}", pairs_file.display());
+
+    let file = File::open(pairs_file)?;
+    let mut reader = BufReader::new(file);
+    let _header = read_pairs_header(&mut reader)?;
+
+    let mut stats = PairsStats::default();
+    for window in DISTANCE_BIN_EDGES.windows(2) {
+        stats.distance_bins.push((bin_label(window[0], window[1]), 0));
+    }
+    for orientation in ["FF", "RR", "FR", "RF", "??"] {
+        stats.orientation_counts.insert(orientation, 0);
+    }
+
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let bytes_read = reader.read_line(&mut line)?;
+        if bytes_read == 0 {
+            break;
+        }
+        let record = match PairRecord::parse(line.trim_end()) {
+            Some(record) => record,
+            None => continue,
+        };
+
+        stats.total_pairs += 1;
+        if record.is_duplicate {
+            stats.duplicate_pairs += 1;
+        }
+        *stats.orientation_counts.entry(record.orientation()).or_insert(0) += 1;
+
+        if let Some(distance) = record.contact_distance() {
+            stats.cis_pairs += 1;
+            for (bin_idx, edge) in DISTANCE_BIN_EDGES.iter().enumerate().skip(1) {
+                if distance < *edge {
+                    stats.distance_bins[bin_idx - 1].1 += 1;
+                    break;
+                }
+            }
+        } else {
+            stats.trans_pairs += 1;
+        }
+    }
+
+    Ok(stats)
+}
+
+/// Write `stats` to `out_file`, as JSON when `json` is set or as TSV
+/// otherwise.
+pub fn write_stats(stats: &PairsStats, out_file: &Path, json: bool) -> Result<(), Box<dyn Error>> {
+    let mut file = File::create(out_file)?;
+    if json {
+        writeln!(file, "{}", stats.to_json())?;
+    } else {
+        write!(file, "{}", stats.to_tsv())?;
+    }
+    Ok(())
+}